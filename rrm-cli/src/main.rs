@@ -5,7 +5,7 @@ mod steam_cmd;
 
 #[tokio::main]
 async fn main() {
-    let steam_cmd::Handle { mut events } = steam_cmd::Steam::builder()
+    let steam_cmd::Handle { ref mut events, .. } = steam_cmd::Steam::builder()
         .home(PathBuf::from_str("/home/ae/configs/\"      \"").unwrap())
         .exe(PathBuf::from_str("/home/ae/.config/rrm/steamcmd/steamcmd.sh").unwrap())
         .add_item(steam_cmd::Item {
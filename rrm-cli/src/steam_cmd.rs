@@ -1,15 +1,88 @@
-use core::panic;
-use std::{path::PathBuf, process::Stdio, str::FromStr};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    process::Stdio,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
 
 use derive_builder::Builder;
 use tokio::{
-    io::{AsyncBufReadExt, BufReader},
-    process::Command,
-    sync::mpsc,
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdout, Command},
+    sync::{mpsc, watch, Mutex},
 };
 
+/// Upper bound on the exponential backoff between retries, regardless of
+/// `base_delay` and the current attempt count
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
 pub struct Handle {
     pub events: mpsc::Receiver<Event>,
+    /// Feed further work into the running steamcmd session
+    pub commands: mpsc::Sender<SessionCommand>,
+    events_tx: mpsc::Sender<Event>,
+    child: Arc<StdMutex<Child>>,
+    cancel: watch::Sender<bool>,
+}
+
+impl Handle {
+    /// Answers a pending [`Event::SteamGuardRequired`] prompt with the code
+    /// the user was sent
+    pub async fn submit_steam_guard_code(
+        &self,
+        code: String,
+    ) -> Result<(), mpsc::error::SendError<SessionCommand>> {
+        self.commands
+            .send(SessionCommand::SteamGuardCode(code))
+            .await
+    }
+
+    /// Stops the session: the stdout/stderr/stdin tasks are told to exit and
+    /// the child steamcmd process is terminated
+    pub fn cancel(&self) {
+        self.teardown();
+    }
+
+    /// Shared by [`Handle::cancel`] and [`Drop`]. Both queue an
+    /// [`Event::Cancelled`], but on drop the `events` receiver goes away with
+    /// the handle, so only a caller that used `cancel()` can receive it
+    fn teardown(&self) {
+        _ = self.cancel.send(true);
+        _ = self.events_tx.try_send(Event::Cancelled);
+        terminate_child(&self.child);
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.teardown();
+    }
+}
+
+/// Sends SIGTERM to the child on Unix, falling back to a hard kill if that
+/// fails or on platforms without signals. Shells out to `kill` rather than
+/// depending on a signal crate, since nothing else in this crate does.
+fn terminate_child(child: &Arc<StdMutex<Child>>) {
+    let Ok(mut child) = child.lock() else {
+        return;
+    };
+
+    #[cfg(unix)]
+    {
+        if let Some(pid) = child.id() {
+            let sent = std::process::Command::new("kill")
+                .args(["-TERM", &pid.to_string()])
+                .status()
+                .is_ok_and(|status| status.success());
+
+            if sent {
+                return;
+            }
+        }
+    }
+
+    _ = child.start_kill();
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -24,6 +97,79 @@ pub enum OutputLine {
     Normal(String),
 }
 
+/// Work items that can be submitted to a running [`Steam`] session over its
+/// `commands` channel.
+#[derive(Debug)]
+pub enum SessionCommand {
+    /// Download a single workshop item
+    Download(Item),
+    /// Switch the session to an authenticated account
+    Login {
+        username: String,
+        password: String,
+        /// Steam Guard / 2FA code, if already known
+        code: Option<String>,
+    },
+    /// Answers a pending Steam Guard prompt
+    SteamGuardCode(String),
+    /// Ask steamcmd whether an item is already installed
+    Status(Item),
+    /// End the session
+    Quit,
+}
+
+impl SessionCommand {
+    fn into_line(self) -> String {
+        match self {
+            SessionCommand::Download(Item {
+                game: GameId(game),
+                item: ItemId(item),
+            }) => format!("+workshop_download_item {game} {item}"),
+            SessionCommand::Login {
+                username,
+                password,
+                code: Some(code),
+            } => format!("+login {username} {password} {code}"),
+            SessionCommand::Login {
+                username, password, ..
+            } => format!("+login {username} {password}"),
+            SessionCommand::SteamGuardCode(code) => code,
+            SessionCommand::Status(Item {
+                game: GameId(game),
+                item: ItemId(item),
+            }) => format!("+workshop_status {game} {item}"),
+            SessionCommand::Quit => "+quit".to_string(),
+        }
+    }
+}
+
+/// What steamcmd reports about an already-downloaded workshop item
+#[derive(Debug, Clone)]
+pub struct ItemStatus {
+    pub state: String,
+    pub install_dir: PathBuf,
+    pub size_bytes: u64,
+}
+
+/// Accumulates the `state` / `install dir` / `size on disk` lines that make
+/// up a single [`ItemStatus`], since steamcmd reports them one line at a time
+#[derive(Default)]
+struct PartialItemStatus {
+    state: Option<String>,
+    install_dir: Option<PathBuf>,
+    size_bytes: Option<u64>,
+}
+
+impl PartialItemStatus {
+    fn into_status(self) -> Option<ItemStatus> {
+        Some(ItemStatus {
+            state: self.state?,
+            install_dir: self.install_dir?,
+            size_bytes: self.size_bytes?,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum Event {
     /// A line from stdout or stderr from the process
@@ -32,6 +178,23 @@ pub enum Event {
     Starting(ItemId),
     /// The ItemId, the path were it was downloaded and the number of bytes
     Done(ItemId, PathBuf, usize),
+    /// steamcmd printed its `Steam>` prompt, meaning the session is idle and
+    /// ready to accept the next command
+    Ready,
+    /// The ItemId and reason steamcmd reported for the failed download,
+    /// after all retries have been exhausted
+    Failed(ItemId, String),
+    /// The ItemId is being retried, this is how many retries have been
+    /// attempted so far
+    Retrying(ItemId, u32),
+    /// steamcmd is waiting on a Steam Guard / 2FA code; feed one back with
+    /// [`Handle::submit_steam_guard_code`]
+    SteamGuardRequired,
+    /// The session was stopped via [`Handle::cancel`] (or by dropping the
+    /// [`Handle`]) before it finished on its own
+    Cancelled,
+    /// The installed state reported for a [`SessionCommand::Status`] query
+    Status(ItemId, ItemStatus),
 }
 
 #[derive(Clone, Copy)]
@@ -46,9 +209,24 @@ pub struct Steam {
     home: PathBuf,
     /// Where the steamcmd binary is located at
     exe: PathBuf,
-    /// Items to download
+    /// Items to download as soon as the session comes up
     #[builder(setter(custom))]
     items: Vec<Item>,
+    /// How many times a failed download is retried before giving up
+    #[builder(default = "3")]
+    max_retries: u32,
+    /// Base delay used to compute the exponential backoff between retries
+    #[builder(default = "Duration::from_secs(1)")]
+    base_delay: Duration,
+    /// Username to log in with; omit to log in anonymously
+    #[builder(default)]
+    username: Option<String>,
+    /// Password for `username`
+    #[builder(default)]
+    password: Option<String>,
+    /// Steam Guard / 2FA code, if already known
+    #[builder(default)]
+    guard_code: Option<String>,
 }
 
 impl SteamBuilder {
@@ -71,8 +249,32 @@ impl Steam {
         SteamBuilder::default()
     }
 
+    /// Spawns a long-lived steamcmd session. The returned [`Handle`] streams
+    /// [`Event`]s out and accepts [`SessionCommand`]s in, so the same process
+    /// (and login) can be reused across many downloads instead of starting a
+    /// fresh steamcmd for each one.
     pub async fn spawn(self) -> Result<Handle, std::io::Error> {
-        let (tx, rx) = mpsc::channel(100);
+        let (event_tx, event_rx) = mpsc::channel(100);
+        let (command_tx, mut command_rx) = mpsc::channel::<SessionCommand>(100);
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+
+        let max_retries = self.max_retries;
+        let base_delay = self.base_delay;
+
+        // Remembers which GameId an ItemId belongs to, so a failed download
+        // can be resubmitted without the caller having to repeat itself
+        let game_ids: Arc<Mutex<HashMap<usize, GameId>>> = Arc::new(Mutex::new(HashMap::new()));
+        // How many times each ItemId has already been retried
+        let attempts: Arc<Mutex<HashMap<usize, u32>>> = Arc::new(Mutex::new(HashMap::new()));
+        // ItemIds awaiting a status reply, in the order they were queried
+        let pending_status: Arc<Mutex<VecDeque<ItemId>>> = Arc::new(Mutex::new(VecDeque::new()));
+        // The item a status reply is currently being accumulated for, paired
+        // with the lines seen so far. Re-paired from `pending_status` at the
+        // start of each status block (on `StatusState`), so a block that
+        // never completes is simply dropped instead of bleeding into the
+        // next query's result
+        let current_status: Arc<Mutex<Option<(ItemId, PartialItemStatus)>>> =
+            Arc::new(Mutex::new(None));
 
         #[allow(unused_mut)]
         let mut command: Command;
@@ -91,30 +293,23 @@ impl Steam {
 
         command.current_dir(self.home);
 
-        command.args(["+login", "anonymous"]);
-
-        let mut game_id_buff = [0u8; 25];
-        let mut item_id_buff = [0u8; 25];
-
-        for Item {
-            game: GameId(game_id),
-            item: ItemId(item_id),
-        } in self.items
-        {
-            let game_id = write_number_into_buff(&mut game_id_buff, game_id);
-            let item_id = write_number_into_buff(&mut item_id_buff, item_id);
+        match (self.username, self.password) {
+            (Some(username), Some(password)) => {
+                command.arg("+login").arg(username).arg(password);
 
-            command
-                .arg("+workshop_download_item")
-                .arg(game_id)
-                .arg(item_id);
+                if let Some(code) = self.guard_code {
+                    command.arg(code);
+                }
+            }
+            _ => {
+                command.args(["+login", "anonymous"]);
+            }
         }
 
-        command.arg("+quit");
         command
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .stdin(Stdio::null());
+            .stdin(Stdio::piped());
 
         let mut child = command.spawn()?;
 
@@ -128,26 +323,105 @@ impl Steam {
             .take()
             .expect("Taking stderr should not fail since its performed only here");
 
+        let mut stdin = child
+            .stdin
+            .take()
+            .expect("Taking stdin should not fail since its performed only here");
+
+        let child = Arc::new(StdMutex::new(child));
+
         // Spawn the stdout handler
         tokio::spawn({
-            let tx = tx.clone();
+            let tx = event_tx.clone();
+            let command_tx = command_tx.clone();
+            let game_ids = Arc::clone(&game_ids);
+            let attempts = Arc::clone(&attempts);
+            let pending_status = Arc::clone(&pending_status);
+            let current_status = Arc::clone(&current_status);
+            let mut cancel_rx = cancel_rx.clone();
 
             async move {
-                let mut lines = BufReader::new(stdout).lines();
-
-                while let Ok(Some(line)) = lines.next_line().await {
-                    let mut words = line.split(' ').peekable();
-
-                    while let Some(word) = words.next() {
-                        if word.trim() == "Downloading"
-                            && words.peek().is_some_and(|w| w == &"item")
-                        {
-                            handle_download_start(&tx, &mut words).await;
-                        } else if word.trim() == "Downloaded"
-                            && words.peek().is_some_and(|w| w == &"item")
-                        {
-                            handle_download_end(&tx, &mut words).await;
+                let mut stdout = stdout;
+                let mut pending = Vec::new();
+
+                loop {
+                    let chunk = tokio::select! {
+                        _ = cancel_rx.changed() => break,
+                        chunk = read_stdout_chunk(&mut stdout, &mut pending) => chunk,
+                    };
+
+                    let Some(line) = chunk else { break };
+
+                    if line.trim() == "Steam>" {
+                        _ = tx.send(Event::Ready).await;
+                        continue;
+                    }
+
+                    if line.trim_end().ends_with("Steam Guard code:") {
+                        _ = tx.send(Event::SteamGuardRequired).await;
+                        continue;
+                    }
+
+                    match parse_line(&tokenize(&line)) {
+                        Some(ParsedLine::Downloading(item_id)) => {
+                            _ = tx.send(Event::Starting(item_id)).await;
+                        }
+                        Some(ParsedLine::Downloaded(item_id, path, size)) => {
+                            attempts.lock().await.remove(&item_id.0);
+                            _ = tx.send(Event::Done(item_id, path, size)).await;
+                        }
+                        Some(ParsedLine::DownloadFailed(item_id, reason)) => {
+                            retry_or_fail(
+                                item_id,
+                                reason,
+                                &tx,
+                                &command_tx,
+                                &game_ids,
+                                &attempts,
+                                max_retries,
+                                base_delay,
+                            )
+                            .await;
+                        }
+                        Some(ParsedLine::StatusState(state)) => {
+                            // A `state:` line starts a new status block, so
+                            // pair it with the next pending query and drop
+                            // whatever the previous block had accumulated
+                            let item_id = pending_status.lock().await.pop_front();
+
+                            *current_status.lock().await = item_id.map(|item_id| {
+                                (
+                                    item_id,
+                                    PartialItemStatus {
+                                        state: Some(state),
+                                        ..Default::default()
+                                    },
+                                )
+                            });
                         }
+                        Some(ParsedLine::StatusInstallDir(dir)) => {
+                            if let Some((_, partial)) = current_status.lock().await.as_mut() {
+                                partial.install_dir = Some(dir);
+                            }
+                        }
+                        Some(ParsedLine::StatusSizeBytes(size)) => {
+                            let finished = {
+                                let mut current = current_status.lock().await;
+
+                                if let Some((_, partial)) = current.as_mut() {
+                                    partial.size_bytes = Some(size);
+                                }
+
+                                current.take()
+                            };
+
+                            if let Some((item_id, partial)) = finished {
+                                if let Some(status) = partial.into_status() {
+                                    _ = tx.send(Event::Status(item_id, status)).await;
+                                }
+                            }
+                        }
+                        None => {}
                     }
 
                     _ = tx.send(Event::Output(OutputLine::Normal(line))).await;
@@ -157,96 +431,348 @@ impl Steam {
 
         // Spawn the stderr handler
         tokio::spawn({
-            let tx = tx.clone();
+            let tx = event_tx.clone();
+            let mut cancel_rx = cancel_rx.clone();
 
             async move {
                 let mut lines = BufReader::new(stderr).lines();
 
-                while let Ok(Some(line)) = lines.next_line().await {
+                loop {
+                    let line = tokio::select! {
+                        _ = cancel_rx.changed() => break,
+                        line = lines.next_line() => line,
+                    };
+
+                    let Ok(Some(line)) = line else { break };
+
                     _ = tx.send(Event::Output(OutputLine::Error(line))).await;
                 }
             }
         });
 
-        Ok(Handle { events: rx })
+        // Spawn the stdin writer: forwards SessionCommands written to the
+        // channel onto the child's stdin as steamcmd script lines
+        tokio::spawn({
+            let game_ids = Arc::clone(&game_ids);
+            let pending_status = Arc::clone(&pending_status);
+            let mut cancel_rx = cancel_rx.clone();
+
+            async move {
+                loop {
+                    let command = tokio::select! {
+                        _ = cancel_rx.changed() => break,
+                        command = command_rx.recv() => command,
+                    };
+
+                    let Some(command) = command else { break };
+
+                    let quit = matches!(command, SessionCommand::Quit);
+
+                    if let SessionCommand::Download(Item {
+                        game,
+                        item: ItemId(item),
+                    }) = &command
+                    {
+                        game_ids.lock().await.insert(*item, *game);
+                    }
+
+                    if let SessionCommand::Status(Item { item, .. }) = &command {
+                        pending_status.lock().await.push_back(*item);
+                    }
+
+                    let line = command.into_line();
+
+                    if stdin.write_all(line.as_bytes()).await.is_err() {
+                        break;
+                    }
+                    if stdin.write_all(b"\n").await.is_err() {
+                        break;
+                    }
+                    if stdin.flush().await.is_err() {
+                        break;
+                    }
+
+                    if quit {
+                        break;
+                    }
+                }
+            }
+        });
+
+        for item in self.items {
+            _ = command_tx.send(SessionCommand::Download(item)).await;
+        }
+
+        Ok(Handle {
+            events: event_rx,
+            commands: command_tx,
+            events_tx,
+            child,
+            cancel: cancel_tx,
+        })
     }
 }
 
-async fn handle_download_end(
-    tx: &mpsc::Sender<Event>,
-    words: &mut std::iter::Peekable<std::str::Split<'_, char>>,
-) {
-    _ = words.next();
-    // Skip "item"
-    let item_id = words
-        .next()
-        .map(|id| {
-            id.trim()
-                .parse::<usize>()
-                .expect("Steam should always provide valid Item IDs")
-        })
-        .expect("Expected \"Downloaded item ITEM_ID\"");
+/// Reads the next complete chunk off `reader`, where a chunk is either a
+/// newline-terminated line (the newline stripped) or, once `pending` already
+/// ends with a prompt steamcmd writes without a trailing newline (`Steam>`,
+/// `...Steam Guard code:`), the unterminated buffer itself. Returns `None` on
+/// EOF once `pending` has been drained.
+///
+/// `pending` holds raw, not-yet-decoded bytes so that a multi-byte UTF-8
+/// sequence split across two reads isn't independently lossy-decoded on
+/// both sides of the split; it's only decoded once a full line (or EOF) is
+/// buffered.
+async fn read_stdout_chunk(reader: &mut ChildStdout, pending: &mut Vec<u8>) -> Option<String> {
+    loop {
+        if let Some(pos) = pending.iter().position(|&byte| byte == b'\n') {
+            let line = pending.drain(..=pos).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line);
+            return Some(line.trim_end_matches(['\n', '\r']).to_string());
+        }
+
+        let decoded = String::from_utf8_lossy(pending);
+        let trimmed = decoded.trim_end();
+        if trimmed == "Steam>" || trimmed.ends_with("Steam Guard code:") {
+            let chunk = decoded.into_owned();
+            pending.clear();
+            return Some(chunk);
+        }
 
-    _ = words.next();
-    // Skip "to"
+        let mut buf = [0u8; 4096];
+        let n = reader.read(&mut buf).await.ok()?;
+
+        if n == 0 {
+            return if pending.is_empty() {
+                None
+            } else {
+                let chunk = String::from_utf8_lossy(pending).into_owned();
+                pending.clear();
+                Some(chunk)
+            };
+        }
 
-    let mut path = words
-        .next()
-        .expect("Expected \"Downlaoded item ITEM_ID to \"PATH\"")
-        .to_string();
+        pending.extend_from_slice(&buf[..n]);
+    }
+}
 
-    let size = loop {
-        let Some(curr) = words.next() else {
-            panic!("Never reached \"(BYTES bytes)\" in stdout");
-        };
-        let next = words.peek();
+/// A line from steamcmd's stdout, parsed into one of the shapes we act on
+enum ParsedLine {
+    Downloading(ItemId),
+    Downloaded(ItemId, PathBuf, usize),
+    DownloadFailed(ItemId, String),
+    StatusState(String),
+    StatusInstallDir(PathBuf),
+    StatusSizeBytes(u64),
+}
+
+/// Splits a line into whitespace-separated tokens, keeping `"quoted spans"`
+/// (e.g. install paths containing spaces) as a single token
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let bytes = line.as_bytes();
+    let mut i = 0;
 
-        if next.is_some_and(|next| next.trim() == "bytes)") {
-            let bytes = curr
-                .trim_start_matches("(")
-                .parse::<usize>()
-                .expect("Steamcmd should always report valid bytes size");
+    while i < bytes.len() {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
 
-            words.next(); // Skip "bytes)"
+        if i >= bytes.len() {
+            break;
+        }
 
-            break bytes;
+        let start = i;
+
+        if bytes[i] == b'"' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != b'"' {
+                i += 1;
+            }
+            if i < bytes.len() {
+                i += 1; // Include the closing quote
+            }
         } else {
-            path += " ";
-            path += curr;
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
         }
+
+        tokens.push(&line[start..i]);
+    }
+
+    tokens
+}
+
+fn parse_item_id(token: &str) -> Option<ItemId> {
+    token.parse::<usize>().ok().map(ItemId)
+}
+
+/// Matches a tokenized stdout line against the shapes steamcmd is known to
+/// emit. Lines that don't match any known shape simply return `None` instead
+/// of panicking, since steamcmd's output format is not a stable contract.
+fn parse_line(tokens: &[&str]) -> Option<ParsedLine> {
+    // steamcmd prefixes completion lines with a "Success." token we don't
+    // otherwise act on
+    let tokens = match tokens {
+        ["Success.", rest @ ..] => rest,
+        _ => tokens,
     };
 
-    let path = PathBuf::from_str(&path[1..path.len() - 1]).unwrap();
+    match tokens {
+        ["Downloading", "item", id] => Some(ParsedLine::Downloading(parse_item_id(id)?)),
+
+        ["Downloaded", "item", id, "to", path, size, "bytes)"] => {
+            let item_id = parse_item_id(id)?;
+            let path = PathBuf::from(path.trim_matches('"'));
+            let size = size.trim_start_matches('(').parse::<usize>().ok()?;
+
+            Some(ParsedLine::Downloaded(item_id, path, size))
+        }
+
+        ["ERROR!", "Download", "item", id, "failed", reason @ ..] => {
+            let item_id = parse_item_id(id)?;
+            let reason = reason
+                .join(" ")
+                .trim_start_matches('(')
+                .trim_end_matches('.')
+                .trim_end_matches(')')
+                .to_string();
+
+            Some(ParsedLine::DownloadFailed(item_id, reason))
+        }
 
-    _ = tx.send(Event::Done(ItemId(item_id), path, size)).await;
+        ["-", "state:", state @ ..] if !state.is_empty() => {
+            Some(ParsedLine::StatusState(state.join(" ")))
+        }
+
+        ["-", "install", "dir:", dir] => Some(ParsedLine::StatusInstallDir(PathBuf::from(
+            dir.trim_matches('"'),
+        ))),
+
+        // The line continues with `bytes, BuildID N`, which we don't act on
+        ["-", "size", "on", "disk:", size, ..] => {
+            Some(ParsedLine::StatusSizeBytes(size.parse().ok()?))
+        }
+
+        _ => None,
+    }
 }
 
-async fn handle_download_start(
+/// Either schedules a retry for `item_id` with an exponential backoff, or
+/// emits [`Event::Failed`] once `max_retries` has been exhausted
+#[allow(clippy::too_many_arguments)]
+async fn retry_or_fail(
+    item_id: ItemId,
+    reason: String,
     tx: &mpsc::Sender<Event>,
-    words: &mut std::iter::Peekable<std::str::Split<'_, char>>,
+    command_tx: &mpsc::Sender<SessionCommand>,
+    game_ids: &Arc<Mutex<HashMap<usize, GameId>>>,
+    attempts: &Arc<Mutex<HashMap<usize, u32>>>,
+    max_retries: u32,
+    base_delay: Duration,
 ) {
-    _ = words.next();
-    // Skip "item"
-    let item_id = words
-        .next()
-        .map(|id| {
-            id.trim()
-                .parse::<usize>()
-                .expect("Steam should always provide valid Item IDs")
-        })
-        .expect("Expected \"Downloading item ITEM_ID\"");
+    let ItemId(id) = item_id;
 
-    _ = tx.send(Event::Starting(ItemId(item_id))).await
+    let attempt = {
+        let mut attempts = attempts.lock().await;
+        let attempt = attempts.entry(id).or_insert(0);
+        *attempt += 1;
+        *attempt
+    };
+
+    let Some(game) = game_ids.lock().await.get(&id).copied() else {
+        attempts.lock().await.remove(&id);
+        _ = tx.send(Event::Failed(item_id, reason)).await;
+        return;
+    };
+
+    if attempt > max_retries {
+        attempts.lock().await.remove(&id);
+        _ = tx.send(Event::Failed(item_id, reason)).await;
+        return;
+    }
+
+    _ = tx.send(Event::Retrying(item_id, attempt)).await;
+
+    let delay = base_delay
+        .checked_mul(2u32.saturating_pow(attempt - 1))
+        .unwrap_or(MAX_RETRY_DELAY)
+        .min(MAX_RETRY_DELAY);
+
+    tokio::spawn({
+        let tx = tx.clone();
+        let command_tx = command_tx.clone();
+
+        async move {
+            tokio::time::sleep(delay).await;
+
+            if command_tx
+                .send(SessionCommand::Download(Item {
+                    game,
+                    item: item_id,
+                }))
+                .await
+                .is_err()
+            {
+                _ = tx.send(Event::Failed(item_id, reason)).await;
+            }
+        }
+    });
 }
 
-fn write_number_into_buff(buff: &mut [u8], value: usize) -> &str {
-    use std::io::{Cursor, Write};
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_workshop_status_lines() {
+        let state = parse_line(&tokenize("- state: 4 (fully installed, update required)")).unwrap();
+        let ParsedLine::StatusState(state) = state else {
+            panic!("expected StatusState");
+        };
+        assert_eq!(state, "4 (fully installed, update required)");
+
+        let dir = parse_line(&tokenize(
+            "- install dir: \"/home/user/Steam/steamapps/workshop/content/294100/123\"",
+        ))
+        .unwrap();
+        let ParsedLine::StatusInstallDir(dir) = dir else {
+            panic!("expected StatusInstallDir");
+        };
+        assert_eq!(
+            dir,
+            PathBuf::from("/home/user/Steam/steamapps/workshop/content/294100/123")
+        );
+
+        let size = parse_line(&tokenize(
+            "- size on disk: 104857600 bytes, BuildID 9876543",
+        ))
+        .unwrap();
+        let ParsedLine::StatusSizeBytes(size) = size else {
+            panic!("expected StatusSizeBytes");
+        };
+        assert_eq!(size, 104_857_600);
+    }
 
-    let mut cursor = Cursor::new(buff);
+    #[test]
+    fn ignores_unrelated_lines() {
+        assert!(parse_line(&tokenize("Success. Download item 123 complete.")).is_none());
+    }
+
+    #[test]
+    fn parses_prefixed_download_completion_line() {
+        let line = parse_line(&tokenize(
+            "Success. Downloaded item 123 to \"/home/user/item\" (456 bytes)",
+        ))
+        .unwrap();
 
-    write!(cursor, "{value}").unwrap();
-    let pos = cursor.position();
-    let buffer = cursor.into_inner();
+        let ParsedLine::Downloaded(item_id, path, size) = line else {
+            panic!("expected Downloaded");
+        };
 
-    std::str::from_utf8(&buffer[..pos as usize]).unwrap()
+        assert_eq!(item_id.0, 123);
+        assert_eq!(path, PathBuf::from("/home/user/item"));
+        assert_eq!(size, 456);
+    }
 }